@@ -7,12 +7,17 @@ use frame_support::{
     decl_error, decl_event, decl_module, decl_storage,
     dispatch::{DispatchError, DispatchResult},
     ensure,
-    traits::Randomness,
-    RuntimeDebug, StorageDoubleMap, StorageValue,
+    traits::{Currency, ExistenceRequirement, Get, Randomness},
+    RuntimeDebug, StorageDoubleMap, StorageMap, StorageValue,
 };
 use frame_system::ensure_signed;
 use sp_io::hashing::blake2_128;
 
+type BalanceOf<T> =
+    <<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::Balance;
+
+#[cfg(test)]
+mod mock;
 #[cfg(test)]
 mod tests;
 
@@ -24,32 +29,86 @@ pub enum KittyGender {
 
 // RuntimeDebug is just like Debug in native build, but becomes simplified version
 // in wasm build.
+//
+// New fields are appended after `dna` so that `Encode`/`Decode` derive output
+// stays stable for existing fields.
 #[derive(Encode, Decode, Clone, RuntimeDebug, PartialEq, Eq)]
-pub struct Kitty(pub [u8; 16]);
+pub struct Kitty {
+    pub dna: [u8; 16],
+    pub generation: u16,
+    pub parents: Option<(u32, u32)>,
+}
 
 impl Kitty {
     pub fn gender(&self) -> KittyGender {
-        if self.0[0] % 2 == 0 {
+        if self.dna[0] % 2 == 0 {
             KittyGender::Male
         } else {
             KittyGender::Female
         }
     }
+
+    // Lets front-ends walk the pedigree without decoding storage by hand.
+    pub fn generation(&self) -> u16 {
+        self.generation
+    }
+
+    pub fn parents(&self) -> Option<(u32, u32)> {
+        self.parents
+    }
+}
+
+// A cross-consensus message describing a kitty lifecycle event, for
+// off-chain workers or other chains to consume without scraping events.
+#[derive(Encode, Decode, Clone, RuntimeDebug, PartialEq, Eq)]
+pub enum OutboundKittyMessage<AccountId> {
+    Created { kitty_id: u32, dna: [u8; 16] },
+    Bred { kitty_id: u32, parents: (u32, u32) },
+    Transferred { kitty_id: u32, dest: AccountId },
+}
+
+// Sink that kitty lifecycle messages are dispatched to. Integrators plug in
+// an XCMP or phala-style message queue; the default `()` impl drops them.
+pub trait HandleKittyMessage<AccountId> {
+    fn dispatch(msg: OutboundKittyMessage<AccountId>);
+}
+
+impl<AccountId> HandleKittyMessage<AccountId> for () {
+    fn dispatch(_msg: OutboundKittyMessage<AccountId>) {}
 }
 
 // Inherits from --vvvvvvvvvvvvvvvv
 pub trait Trait: frame_system::Trait {
     type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+
+    // The currency used to settle marketplace trades.
+    type Currency: Currency<Self::AccountId>;
+
+    // Chance, out of 256, that any single DNA bit is replaced by a fresh bit
+    // during breeding (the fresh bit may equal the inherited one).
+    type MutationRate: Get<u8>;
+
+    // Where outbound kitty lifecycle messages are sent.
+    type MessageSink: HandleKittyMessage<Self::AccountId>;
 }
 
 decl_event! {
     pub enum Event<T> where
         <T as frame_system::Trait>::AccountId,
+        Balance = BalanceOf<T>,
     {
         // A kitty is created. \[owner, kitty_id, kitty\]
         KittyCreated(AccountId, u32, Kitty),
-        // A new kitten is bred. \[owner, kitty_id, kitty\]
-        KittyBred(AccountId, u32, Kitty),
+        // A new kitten is bred. \[owner, kitty_id, kitty, generation\]
+        KittyBred(AccountId, u32, Kitty, u16),
+        // A kitty is listed for sale. \[owner, kitty_id, price\]
+        KittyListed(AccountId, u32, Balance),
+        // A kitty is taken off the market. \[owner, kitty_id\]
+        KittyUnlisted(AccountId, u32),
+        // A kitty is sold. \[seller, buyer, kitty_id, price\]
+        KittySold(AccountId, AccountId, u32, Balance),
+        // A kitty is transferred. \[from, to, kitty_id\]
+        KittyTransferred(AccountId, AccountId, u32),
     }
 }
 
@@ -58,6 +117,9 @@ decl_error! {
         KittiesIdOverflow,
         InvalidKittyId,
         SameGender,
+        KittyNotForSale,
+        MaxPriceTooLow,
+        GenerationOverflow,
     }
 }
 
@@ -76,6 +138,31 @@ decl_storage! {
 
         // Stores the next kitty id
         pub NextKittyId get(fn next_kitty_id): u32;
+
+        // Stores the listed price of a kitty, if it is currently for sale.
+        pub KittyPrices get(fn kitty_price): map
+            hasher(blake2_128_concat) u32 => Option<BalanceOf<T>>;
+
+        // Authoritative owner lookup, independent of the `Kitties` map key.
+        pub KittyOwner get(fn kitty_owner): map
+            hasher(blake2_128_concat) u32 => Option<T::AccountId>;
+
+        // How many kitties each account currently owns.
+        pub OwnedKittiesCount get(fn owned_kitties_count): map
+            hasher(blake2_128_concat) T::AccountId => u32;
+
+        // How many kitties exist in total.
+        pub AllKittiesCount get(fn all_kitties_count): u32;
+
+        // Contiguous per-owner slot => kitty id, so a UI can paginate an
+        // owner's kitties without scanning the whole `Kitties` map.
+        pub OwnedKittiesIndex get(fn owned_kitties_index): double_map
+            hasher(blake2_128_concat) T::AccountId,
+            hasher(blake2_128_concat) u32 => u32;
+
+        // Reverse of `OwnedKittiesIndex`: kitty id => its current slot, so a
+        // removal can swap-and-pop in O(1) instead of scanning for the slot.
+        KittyOwnedSlot: map hasher(blake2_128_concat) u32 => u32;
     }
 }
 
@@ -99,9 +186,17 @@ decl_module! {
             let dna = Self::random_value(&sender);
 
             // Create and store kitty
-            let kitty = Kitty(dna);
+            let kitty = Kitty {
+                dna,
+                generation: 0,
+                parents: None,
+            };
             Kitties::<T>::insert(&sender, kitty_id, kitty.clone());
             // <Kitties<T>>::insert(&sender, current_id, kitty.clone());
+            KittyOwner::<T>::insert(kitty_id, &sender);
+            Self::add_kitty_to_owner(&sender, kitty_id)?;
+
+            T::MessageSink::dispatch(OutboundKittyMessage::Created { kitty_id, dna });
 
             // Emit event
             Self::deposit_event(RawEvent::KittyCreated(sender, kitty_id, kitty));
@@ -118,22 +213,95 @@ decl_module! {
             ensure!(kitty1.gender() != kitty2.gender(), Error::<T>::SameGender);
             let kitty_id = Self::get_next_kitty_id()?;
 
-            let kitty1_dna = kitty1.0;
-            let kitty2_dna = kitty2.0;
+            let kitty1_dna = kitty1.dna;
+            let kitty2_dna = kitty2.dna;
+            let generation = kitty1
+                .generation
+                .max(kitty2.generation)
+                .checked_add(1)
+                .ok_or(Error::<T>::GenerationOverflow)?;
 
-            // Generate random 128bit value to use as kitty DNA.
-            let selector = Self::random_value(&sender);
-            let mut new_dna = [0u8; 16];
+            let new_dna = Self::breed_dna(kitty_id, &kitty1_dna, &kitty2_dna);
+
+            let new_kitty = Kitty {
+                dna: new_dna,
+                generation,
+                parents: Some((kitty_id_1, kitty_id_2)),
+            };
+            Kitties::<T>::insert(&sender, kitty_id, &new_kitty);
+            KittyOwner::<T>::insert(kitty_id, &sender);
+            Self::add_kitty_to_owner(&sender, kitty_id)?;
+
+            T::MessageSink::dispatch(OutboundKittyMessage::Bred {
+                kitty_id,
+                parents: (kitty_id_1, kitty_id_2),
+            });
+
+            Self::deposit_event(RawEvent::KittyBred(sender, kitty_id, new_kitty, generation));
+        }
+
+        #[weight = 1000]
+        pub fn transfer(origin, to: T::AccountId, kitty_id: u32) {
+            let sender = ensure_signed(origin)?;
+            let kitty = Self::kitties(&sender, kitty_id).ok_or(Error::<T>::InvalidKittyId)?;
 
-            // Combine parents and selector to create new kitty:
-            for i in 0..kitty1_dna.len() {
-                new_dna[i] = combine_dna(kitty1_dna[i], kitty2_dna[i], selector[i]);
+            Kitties::<T>::remove(&sender, kitty_id);
+            Kitties::<T>::insert(&to, kitty_id, kitty);
+            KittyOwner::<T>::insert(kitty_id, &to);
+            KittyPrices::<T>::remove(kitty_id);
+            Self::remove_kitty_from_owner(&sender, kitty_id)?;
+            Self::add_kitty_to_owner(&to, kitty_id)?;
+
+            T::MessageSink::dispatch(OutboundKittyMessage::Transferred {
+                kitty_id,
+                dest: to.clone(),
+            });
+
+            Self::deposit_event(RawEvent::KittyTransferred(sender, to, kitty_id));
+        }
+
+        #[weight = 1000]
+        pub fn set_price(origin, kitty_id: u32, price: Option<BalanceOf<T>>) {
+            let sender = ensure_signed(origin)?;
+            ensure!(Kitties::<T>::contains_key(&sender, kitty_id), Error::<T>::InvalidKittyId);
+
+            match price {
+                Some(price) => {
+                    KittyPrices::<T>::insert(kitty_id, price);
+                    Self::deposit_event(RawEvent::KittyListed(sender, kitty_id, price));
+                }
+                None => {
+                    KittyPrices::<T>::remove(kitty_id);
+                    Self::deposit_event(RawEvent::KittyUnlisted(sender, kitty_id));
+                }
             }
+        }
 
-            let new_kitty = Kitty(new_dna);
-            Kitties::<T>::insert(&sender, kitty_id, &new_kitty);
+        #[weight = 1000]
+        pub fn buy(origin, owner: T::AccountId, kitty_id: u32, max_price: BalanceOf<T>) {
+            let buyer = ensure_signed(origin)?;
+            let kitty = Self::kitties(&owner, kitty_id).ok_or(Error::<T>::InvalidKittyId)?;
+            let price = Self::kitty_price(kitty_id).ok_or(Error::<T>::KittyNotForSale)?;
+
+            // Guard against the seller re-listing at a higher price between
+            // when the buyer observed it and when this extrinsic executes.
+            ensure!(max_price >= price, Error::<T>::MaxPriceTooLow);
+
+            T::Currency::transfer(&buyer, &owner, price, ExistenceRequirement::KeepAlive)?;
+
+            Kitties::<T>::remove(&owner, kitty_id);
+            Kitties::<T>::insert(&buyer, kitty_id, kitty);
+            KittyOwner::<T>::insert(kitty_id, &buyer);
+            KittyPrices::<T>::remove(kitty_id);
+            Self::remove_kitty_from_owner(&owner, kitty_id)?;
+            Self::add_kitty_to_owner(&buyer, kitty_id)?;
+
+            T::MessageSink::dispatch(OutboundKittyMessage::Transferred {
+                kitty_id,
+                dest: buyer.clone(),
+            });
 
-            Self::deposit_event(RawEvent::KittyBred(sender, kitty_id, new_kitty));
+            Self::deposit_event(RawEvent::KittySold(owner, buyer, kitty_id, price));
         }
     }
 }
@@ -161,4 +329,83 @@ impl<T: Trait> Module<T> {
         );
         payload.using_encoded(blake2_128)
     }
+
+    // Deterministic given the same parents and child id, so tests can assert
+    // on exact offspring DNA. A small number of bits may still mutate away
+    // from their inherited value, governed by `T::MutationRate`.
+    fn breed_dna(kitty_id: u32, dna1: &[u8; 16], dna2: &[u8; 16]) -> [u8; 16] {
+        let inherit_selector: [u8; 16] =
+            (dna1, dna2, kitty_id, b"inherit").using_encoded(blake2_128);
+        let mutation_selector: [u8; 16] =
+            (dna1, dna2, kitty_id, b"mutate").using_encoded(blake2_128);
+
+        let mut new_dna = [0u8; 16];
+        for i in 0..new_dna.len() {
+            new_dna[i] = combine_dna(dna1[i], dna2[i], inherit_selector[i]);
+        }
+
+        Self::mutate_dna(new_dna, &mutation_selector)
+    }
+
+    fn mutate_dna(mut dna: [u8; 16], mutation_selector: &[u8; 16]) -> [u8; 16] {
+        let threshold = T::MutationRate::get();
+
+        for (i, byte) in dna.iter_mut().enumerate() {
+            for bit in 0..8u8 {
+                // Mix the byte's mutation selector with the bit position so
+                // each bit of the byte gets a different roll. For bit 0 the
+                // additive term and the rotation are both zero, so its roll is
+                // the selector byte itself.
+                let roll = mutation_selector[i]
+                    .wrapping_add(bit.wrapping_mul(41))
+                    .rotate_left(bit as u32);
+                if roll < threshold {
+                    let fresh_bit = (mutation_selector[i].rotate_right(bit as u32) >> bit) & 1;
+                    *byte = (*byte & !(1 << bit)) | (fresh_bit << bit);
+                }
+            }
+        }
+
+        dna
+    }
+
+    fn add_kitty_to_owner(owner: &T::AccountId, kitty_id: u32) -> DispatchResult {
+        let owned_count = Self::owned_kitties_count(owner);
+        let new_owned_count = owned_count
+            .checked_add(1)
+            .ok_or(Error::<T>::KittiesIdOverflow)?;
+        let new_all_count = Self::all_kitties_count()
+            .checked_add(1)
+            .ok_or(Error::<T>::KittiesIdOverflow)?;
+
+        OwnedKittiesIndex::<T>::insert(owner, owned_count, kitty_id);
+        KittyOwnedSlot::insert(kitty_id, owned_count);
+        OwnedKittiesCount::<T>::insert(owner, new_owned_count);
+        AllKittiesCount::put(new_all_count);
+
+        Ok(())
+    }
+
+    fn remove_kitty_from_owner(owner: &T::AccountId, kitty_id: u32) -> DispatchResult {
+        let last_slot = Self::owned_kitties_count(owner)
+            .checked_sub(1)
+            .ok_or(Error::<T>::KittiesIdOverflow)?;
+        let removed_slot = KittyOwnedSlot::take(kitty_id);
+
+        // Swap the last slot into the removed one so the index stays dense.
+        if removed_slot != last_slot {
+            let last_kitty_id = OwnedKittiesIndex::<T>::get(owner, last_slot);
+            OwnedKittiesIndex::<T>::insert(owner, removed_slot, last_kitty_id);
+            KittyOwnedSlot::insert(last_kitty_id, removed_slot);
+        }
+        OwnedKittiesIndex::<T>::remove(owner, last_slot);
+
+        let new_all_count = Self::all_kitties_count()
+            .checked_sub(1)
+            .ok_or(Error::<T>::KittiesIdOverflow)?;
+        OwnedKittiesCount::<T>::insert(owner, last_slot);
+        AllKittiesCount::put(new_all_count);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,119 @@
+use crate::{self as pallet_kitties, HandleKittyMessage, OutboundKittyMessage, Trait};
+use frame_support::{impl_outer_origin, parameter_types, weights::Weight};
+use sp_core::H256;
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, IdentityLookup},
+    Perbill,
+};
+use std::cell::RefCell;
+
+impl_outer_origin! {
+    pub enum Origin for Test {}
+}
+
+#[derive(Clone, Eq, PartialEq)]
+pub struct Test;
+
+parameter_types! {
+    pub const BlockHashCount: u64 = 250;
+    pub const MaximumBlockWeight: Weight = 1024;
+    pub const MaximumBlockLength: u32 = 2 * 1024;
+    pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+    pub const ExistentialDeposit: u64 = 1;
+    pub const MutationRate: u8 = 0;
+}
+
+impl frame_system::Trait for Test {
+    type BaseCallFilter = ();
+    type Origin = Origin;
+    type Call = ();
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = ();
+    type BlockHashCount = BlockHashCount;
+    type MaximumBlockWeight = MaximumBlockWeight;
+    type DbWeight = ();
+    type BlockExecutionWeight = ();
+    type ExtrinsicBaseWeight = ();
+    type MaximumExtrinsicWeight = MaximumBlockWeight;
+    type MaximumBlockLength = MaximumBlockLength;
+    type AvailableBlockRatio = AvailableBlockRatio;
+    type Version = ();
+    type PalletInfo = ();
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+}
+
+impl pallet_balances::Trait for Test {
+    type MaxLocks = ();
+    type Balance = u64;
+    type Event = ();
+    type DustRemoval = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = frame_system::Module<Test>;
+    type WeightInfo = ();
+}
+
+impl pallet_randomness_collective_flip::Trait for Test {}
+
+thread_local! {
+    static SENT_MESSAGES: RefCell<Vec<OutboundKittyMessage<u64>>> = RefCell::new(Vec::new());
+}
+
+// Records every message handed to `MessageSink` instead of dropping it, so
+// tests can assert the right outbound message fired for a given call.
+pub struct RecordingSink;
+
+impl HandleKittyMessage<u64> for RecordingSink {
+    fn dispatch(msg: OutboundKittyMessage<u64>) {
+        SENT_MESSAGES.with(|sent| sent.borrow_mut().push(msg));
+    }
+}
+
+pub fn sent_messages() -> Vec<OutboundKittyMessage<u64>> {
+    SENT_MESSAGES.with(|sent| sent.borrow().clone())
+}
+
+pub fn clear_sent_messages() {
+    SENT_MESSAGES.with(|sent| sent.borrow_mut().clear());
+}
+
+impl Trait for Test {
+    type Event = ();
+    type Currency = pallet_balances::Module<Test>;
+    type MutationRate = MutationRate;
+    type MessageSink = RecordingSink;
+}
+
+pub type Kitties = pallet_kitties::Module<Test>;
+pub type Balances = pallet_balances::Module<Test>;
+
+// Builds a genesis storage for a `TestExternalities` without any accounts.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    frame_system::GenesisConfig::default()
+        .build_storage::<Test>()
+        .unwrap()
+        .into()
+}
+
+// Same as `new_test_ext`, but seeds the given accounts with a starting
+// balance so marketplace tests can move real `T::Currency` funds.
+pub fn new_test_ext_with_balances(balances: Vec<(u64, u64)>) -> sp_io::TestExternalities {
+    let mut storage = frame_system::GenesisConfig::default()
+        .build_storage::<Test>()
+        .unwrap();
+
+    pallet_balances::GenesisConfig::<Test> { balances }
+        .assimilate_storage(&mut storage)
+        .unwrap();
+
+    storage.into()
+}
@@ -0,0 +1,228 @@
+use crate::mock::{
+    clear_sent_messages, new_test_ext, new_test_ext_with_balances, sent_messages, Balances,
+    Kitties, Test,
+};
+use crate::{
+    AllKittiesCount, Error, Kitties as KittiesStorage, Kitty, KittyOwner, KittyPrices, Module,
+    NextKittyId, OutboundKittyMessage, OwnedKittiesCount, OwnedKittiesIndex,
+};
+use frame_support::{assert_noop, assert_ok, StorageDoubleMap, StorageMap, StorageValue};
+
+fn insert_kitty(owner: u64, kitty_id: u32, dna: [u8; 16]) {
+    let kitty = Kitty {
+        dna,
+        generation: 0,
+        parents: None,
+    };
+    KittiesStorage::<Test>::insert(&owner, kitty_id, &kitty);
+    KittyOwner::<Test>::insert(kitty_id, &owner);
+    Module::<Test>::add_kitty_to_owner(&owner, kitty_id).unwrap();
+}
+
+#[test]
+fn breed_dna_is_deterministic_given_same_parents_and_id() {
+    new_test_ext().execute_with(|| {
+        let dna1 = [0b1010_1010u8; 16];
+        let dna2 = [0b0101_0101u8; 16];
+
+        let child_a = Module::<Test>::breed_dna(7, &dna1, &dna2);
+        let child_b = Module::<Test>::breed_dna(7, &dna1, &dna2);
+
+        assert_eq!(child_a, child_b);
+    });
+}
+
+#[test]
+fn breed_dna_differs_by_kitty_id() {
+    new_test_ext().execute_with(|| {
+        let dna1 = [0b1010_1010u8; 16];
+        let dna2 = [0b0101_0101u8; 16];
+
+        let child_for_id_0 = Module::<Test>::breed_dna(0, &dna1, &dna2);
+        let child_for_id_1 = Module::<Test>::breed_dna(1, &dna1, &dna2);
+
+        assert_ne!(child_for_id_0, child_for_id_1);
+    });
+}
+
+#[test]
+fn breed_records_generation_and_parents_and_matches_breed_dna() {
+    new_test_ext().execute_with(|| {
+        let owner = 1u64;
+        // dna1[0] is even (Male), dna2[0] is odd (Female).
+        let dna1 = [0u8; 16];
+        let dna2 = [1u8; 16];
+
+        insert_kitty(owner, 0, dna1);
+        insert_kitty(owner, 1, dna2);
+        NextKittyId::put(2);
+
+        assert_ok!(Kitties::breed(
+            frame_system::RawOrigin::Signed(owner).into(),
+            0,
+            1
+        ));
+
+        let child = KittiesStorage::<Test>::get(&owner, 2).unwrap();
+        assert_eq!(child.generation, 1);
+        assert_eq!(child.parents, Some((0, 1)));
+        assert_eq!(child.dna, Module::<Test>::breed_dna(2, &dna1, &dna2));
+    });
+}
+
+#[test]
+fn create_dispatches_created_message() {
+    new_test_ext().execute_with(|| {
+        clear_sent_messages();
+
+        assert_ok!(Kitties::create(frame_system::RawOrigin::Signed(1).into()));
+
+        let messages = sent_messages();
+        assert_eq!(messages.len(), 1);
+        match &messages[0] {
+            OutboundKittyMessage::Created { kitty_id, .. } => assert_eq!(*kitty_id, 0),
+            other => panic!("unexpected message: {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn breed_dispatches_bred_message() {
+    new_test_ext().execute_with(|| {
+        insert_kitty(1, 0, [0u8; 16]);
+        insert_kitty(1, 1, [1u8; 16]);
+        NextKittyId::put(2);
+        clear_sent_messages();
+
+        assert_ok!(Kitties::breed(
+            frame_system::RawOrigin::Signed(1).into(),
+            0,
+            1
+        ));
+
+        assert_eq!(
+            sent_messages(),
+            vec![OutboundKittyMessage::Bred {
+                kitty_id: 2,
+                parents: (0, 1),
+            }]
+        );
+    });
+}
+
+#[test]
+fn transfer_dispatches_transferred_message() {
+    new_test_ext().execute_with(|| {
+        insert_kitty(1, 0, [5u8; 16]);
+        clear_sent_messages();
+
+        assert_ok!(Kitties::transfer(
+            frame_system::RawOrigin::Signed(1).into(),
+            2,
+            0
+        ));
+
+        assert_eq!(
+            sent_messages(),
+            vec![OutboundKittyMessage::Transferred {
+                kitty_id: 0,
+                dest: 2,
+            }]
+        );
+    });
+}
+
+#[test]
+fn buy_transfers_funds_clears_price_and_updates_counts() {
+    new_test_ext_with_balances(vec![(1, 100), (2, 100)]).execute_with(|| {
+        insert_kitty(1, 0, [9u8; 16]);
+        assert_ok!(Kitties::set_price(
+            frame_system::RawOrigin::Signed(1).into(),
+            0,
+            Some(40)
+        ));
+        clear_sent_messages();
+
+        assert_ok!(Kitties::buy(
+            frame_system::RawOrigin::Signed(2).into(),
+            1,
+            0,
+            40
+        ));
+
+        assert_eq!(Balances::free_balance(1), 140);
+        assert_eq!(Balances::free_balance(2), 60);
+        assert!(KittiesStorage::<Test>::get(&1, 0).is_none());
+        assert!(KittiesStorage::<Test>::get(&2, 0).is_some());
+        assert_eq!(KittyPrices::<Test>::get(0), None);
+        assert_eq!(OwnedKittiesCount::<Test>::get(1), 0);
+        assert_eq!(OwnedKittiesCount::<Test>::get(2), 1);
+        assert_eq!(AllKittiesCount::get(), 1);
+        assert_eq!(
+            sent_messages(),
+            vec![OutboundKittyMessage::Transferred {
+                kitty_id: 0,
+                dest: 2,
+            }]
+        );
+    });
+}
+
+#[test]
+fn buy_fails_when_not_for_sale() {
+    new_test_ext_with_balances(vec![(1, 100), (2, 100)]).execute_with(|| {
+        insert_kitty(1, 0, [9u8; 16]);
+
+        assert_noop!(
+            Kitties::buy(frame_system::RawOrigin::Signed(2).into(), 1, 0, 40),
+            Error::<Test>::KittyNotForSale
+        );
+    });
+}
+
+#[test]
+fn buy_fails_when_max_price_too_low() {
+    new_test_ext_with_balances(vec![(1, 100), (2, 100)]).execute_with(|| {
+        insert_kitty(1, 0, [9u8; 16]);
+        assert_ok!(Kitties::set_price(
+            frame_system::RawOrigin::Signed(1).into(),
+            0,
+            Some(40)
+        ));
+
+        assert_noop!(
+            Kitties::buy(frame_system::RawOrigin::Signed(2).into(), 1, 0, 39),
+            Error::<Test>::MaxPriceTooLow
+        );
+    });
+}
+
+#[test]
+fn transfer_swap_and_pop_keeps_owner_index_dense() {
+    new_test_ext().execute_with(|| {
+        insert_kitty(1, 0, [1u8; 16]);
+        insert_kitty(1, 1, [2u8; 16]);
+        insert_kitty(1, 2, [3u8; 16]);
+
+        assert_eq!(OwnedKittiesCount::<Test>::get(1), 3);
+        assert_eq!(OwnedKittiesIndex::<Test>::get(1, 0), 0);
+        assert_eq!(OwnedKittiesIndex::<Test>::get(1, 1), 1);
+        assert_eq!(OwnedKittiesIndex::<Test>::get(1, 2), 2);
+
+        // Remove the kitty occupying the first (non-last) slot.
+        assert_ok!(Kitties::transfer(
+            frame_system::RawOrigin::Signed(1).into(),
+            2,
+            0
+        ));
+
+        assert_eq!(OwnedKittiesCount::<Test>::get(1), 2);
+        // The former last slot's kitty (id 2) was swapped into the freed slot.
+        assert_eq!(OwnedKittiesIndex::<Test>::get(1, 0), 2);
+        assert_eq!(OwnedKittiesIndex::<Test>::get(1, 1), 1);
+        assert!(!OwnedKittiesIndex::<Test>::contains_key(1, 2));
+
+        assert_eq!(OwnedKittiesCount::<Test>::get(2), 1);
+        assert_eq!(OwnedKittiesIndex::<Test>::get(2, 0), 0);
+    });
+}